@@ -1,11 +1,46 @@
 use std::collections::{HashMap, LinkedList};
-use std::sync::{RwLock, Arc, Mutex};
+use std::sync::{RwLock, Arc, Mutex, MutexGuard};
 use std::vec::{Vec};
 use crate::common::{FrameID, PageID, INVALID_PAGE_ID};
 use crate::storage::page::page::{Page};
 use crate::storage::disk::disk_manager::{DiskManager};
+use crate::storage::hash::ExtendibleHashTable;
 
 use super::lru_k_replacer::{LRUKReplacer};
+use super::page_guard::{BasicPageGuard, ReadPageGuard, WritePageGuard};
+use super::snapshot::{SnapshotID, SnapshotManager};
+
+/// Bucket capacity used for the `page_id -> frame_id` directory. Buffer pools
+/// are sized in the dozens to low thousands of frames, so a handful of entries
+/// per bucket keeps each bucket's linear scan short, at the cost of more
+/// splits and a larger directory than bigger buckets would need.
+const PAGE_TABLE_BUCKET_CAPACITY: usize = 4;
+
+/// Reserved page id holding the free-page-list header: the next page id to
+/// hand out once the free list runs dry, followed by as many currently-freed
+/// page ids as fit in one page. Keeping this at page 0 means
+/// `allocate_page`/`deallocate_page` survive a restart without a separate
+/// metadata file.
+const FREE_LIST_HEADER_PAGE_ID: PageID = 0;
+
+/// Number of page ids, right after the header, permanently reserved to chain
+/// the free-page-id list across multiple pages once it outgrows the header
+/// page's own capacity. Real data pages start after this reserved range, so
+/// growing the free list never collides with a live data page id.
+const FREE_LIST_CHAIN_PAGE_COUNT: usize = 7;
+
+/// Page ids of the reserved free-list chain pages, in link order.
+const FREE_LIST_CHAIN_PAGE_IDS: [PageID; FREE_LIST_CHAIN_PAGE_COUNT] = [1, 2, 3, 4, 5, 6, 7];
+
+/// Sentinel stored in a free-list page's chain pointer when there is no
+/// further page in the chain. Reuses `INVALID_PAGE_ID` since it already means
+/// "not a real page id" everywhere else in this file.
+const FREE_LIST_CHAIN_END: PageID = INVALID_PAGE_ID;
+
+/// First page id available for real data: ids up to and including
+/// `FREE_LIST_CHAIN_PAGE_IDS`'s last entry are reserved for free-list
+/// bookkeeping.
+const FIRST_DATA_PAGE_ID: PageID = 1 + FREE_LIST_CHAIN_PAGE_COUNT as PageID;
 
 struct MetaData {
     pub page_id: PageID,
@@ -13,306 +48,574 @@ struct MetaData {
     pub is_dirty: bool,
 }
 
-#[derive(Default)]
 struct BPMFields {
-    pool_size: usize,
     next_page_id: PageID,
-
-    // log_manager,
-    disk_manager: DiskManager,
-    page_table: HashMap<PageID, FrameID>,
+    page_table: ExtendibleHashTable<PageID, FrameID>,
     meta_data: HashMap<FrameID, MetaData>,
     replacer: LRUKReplacer,
     free_list: LinkedList<FrameID>,
-    pages: Vec<Arc<RwLock<Page>>>,
+    free_page_ids: LinkedList<PageID>,
+    snapshots: SnapshotManager,
 }
 
-pub struct BufferPoolManager {
-    pool_size: usize,
-    next_page_id: PageID,
+impl BPMFields {
+    fn allocate_page(&mut self) -> PageID {
+        if let Some(page_id) = self.free_page_ids.pop_front() {
+            return page_id;
+        }
+        self.next_page_id += 1;
+        self.next_page_id - 1
+    }
+
+    fn deallocate_page(&mut self, page_id: PageID) {
+        self.free_page_ids.push_back(page_id);
+    }
+}
 
+/// How many free page ids fit in a page-sized buffer of `buf_len` bytes,
+/// after `header_bytes` bytes of fixed fields ahead of the id list.
+fn free_ids_capacity(buf_len: usize, header_bytes: usize) -> usize {
+    buf_len.saturating_sub(header_bytes) / std::mem::size_of::<PageID>()
+}
+
+/// Encodes one free-list page's common suffix at `offset`: the chain pointer
+/// to the next free-list page (or `FREE_LIST_CHAIN_END`), the id count, then
+/// the ids themselves, all as little-endian integers. `ids` must already fit
+/// in the space available after `offset`; callers split the full free list
+/// into page-sized chunks before calling this.
+fn encode_free_list_suffix(buf: &mut [u8], offset: usize, chain_next: PageID, ids: &[PageID]) {
+    let id_size = std::mem::size_of::<PageID>();
+    debug_assert!(ids.len() <= free_ids_capacity(buf.len() - offset, id_size + 8));
+
+    buf[offset..offset + id_size].copy_from_slice(&chain_next.to_le_bytes());
+    let count_offset = offset + id_size;
+    buf[count_offset..count_offset + 8].copy_from_slice(&(ids.len() as u64).to_le_bytes());
+
+    let mut id_offset = count_offset + 8;
+    for &page_id in ids {
+        buf[id_offset..id_offset + id_size].copy_from_slice(&page_id.to_le_bytes());
+        id_offset += id_size;
+    }
+}
+
+/// Inverse of `encode_free_list_suffix`. Trusts nothing beyond what `buf` can
+/// actually hold: a corrupted or truncated count is capped to the page's real
+/// capacity rather than driving the read loop past the buffer.
+fn decode_free_list_suffix(buf: &[u8], offset: usize) -> (PageID, Vec<PageID>) {
+    let id_size = std::mem::size_of::<PageID>();
+    let chain_next = PageID::from_le_bytes(buf[offset..offset + id_size].try_into().unwrap());
+    let count_offset = offset + id_size;
+    let capacity = free_ids_capacity(buf.len() - offset, id_size + 8);
+    let count = (u64::from_le_bytes(buf[count_offset..count_offset + 8].try_into().unwrap()) as usize).min(capacity);
+
+    let mut ids = Vec::with_capacity(count);
+    let mut id_offset = count_offset + 8;
+    for _ in 0..count {
+        ids.push(PageID::from_le_bytes(buf[id_offset..id_offset + id_size].try_into().unwrap()));
+        id_offset += id_size;
+    }
+
+    (chain_next, ids)
+}
+
+/// Splits `next_page_id` and the free page id list into one header-page chunk
+/// and as many reserved `FREE_LIST_CHAIN_PAGE_IDS` chunks as it takes to hold
+/// every id, instead of assuming everything fits on the header page alone.
+/// More ids than the reserved chain pages can hold are dropped rather than
+/// corrupting a page buffer or minting fresh page ids to chain further —
+/// reaching that bound would take orders of magnitude more freed pages than
+/// the reserved range already allows for.
+fn encode_free_page_list(disk_manager: &DiskManager, next_page_id: PageID, free_page_ids: &LinkedList<PageID>) {
+    let id_size = std::mem::size_of::<PageID>();
+    let ids: Vec<PageID> = free_page_ids.iter().copied().collect();
+
+    let header_capacity = free_ids_capacity(Page::new().data.len() - id_size, id_size + 8);
+    let chain_capacity = free_ids_capacity(Page::new().data.len(), id_size + 8);
+
+    let mut chunks: Vec<&[PageID]> = Vec::new();
+    let (first, mut rest) = ids.split_at(ids.len().min(header_capacity));
+    chunks.push(first);
+    while !rest.is_empty() && chunks.len() <= FREE_LIST_CHAIN_PAGE_COUNT {
+        let (chunk, more) = rest.split_at(rest.len().min(chain_capacity));
+        chunks.push(chunk);
+        rest = more;
+    }
+
+    let mut header = Page::new();
+    header.data[0..id_size].copy_from_slice(&next_page_id.to_le_bytes());
+    let header_chain_next = if chunks.len() > 1 { FREE_LIST_CHAIN_PAGE_IDS[0] } else { FREE_LIST_CHAIN_END };
+    encode_free_list_suffix(&mut header.data, id_size, header_chain_next, chunks[0]);
+    disk_manager.write_page(FREE_LIST_HEADER_PAGE_ID, &header.data);
+
+    for (i, chunk) in chunks.iter().enumerate().skip(1) {
+        let this_chain_next = if i < chunks.len() - 1 { FREE_LIST_CHAIN_PAGE_IDS[i] } else { FREE_LIST_CHAIN_END };
+        let mut page = Page::new();
+        encode_free_list_suffix(&mut page.data, 0, this_chain_next, chunk);
+        disk_manager.write_page(FREE_LIST_CHAIN_PAGE_IDS[i - 1], &page.data);
+    }
+}
+
+/// Inverse of `encode_free_page_list`: reads the header page, then follows its
+/// chain pointer through the reserved `FREE_LIST_CHAIN_PAGE_IDS` pages until
+/// `FREE_LIST_CHAIN_END` or the reserved range is exhausted, whichever comes
+/// first, so a corrupted chain pointer can't loop forever.
+fn decode_free_page_list(disk_manager: &DiskManager) -> (PageID, LinkedList<PageID>) {
+    let id_size = std::mem::size_of::<PageID>();
+    let mut header = Page::new();
+    disk_manager.read_page(FREE_LIST_HEADER_PAGE_ID, &mut header.data);
+    let next_page_id = PageID::from_le_bytes(header.data[0..id_size].try_into().unwrap());
+    let (mut chain_next, first_ids) = decode_free_list_suffix(&header.data, id_size);
+
+    let mut free_page_ids: LinkedList<PageID> = first_ids.into_iter().collect();
+    let mut visited = 0;
+    while chain_next != FREE_LIST_CHAIN_END && visited < FREE_LIST_CHAIN_PAGE_COUNT {
+        let mut page = Page::new();
+        disk_manager.read_page(chain_next, &mut page.data);
+        let (next_chain_next, ids) = decode_free_list_suffix(&page.data, 0);
+        free_page_ids.extend(ids);
+        chain_next = next_chain_next;
+        visited += 1;
+    }
+
+    (next_page_id, free_page_ids)
+}
+
+/// The buffer pool manager has a single, thread-safe implementation: every
+/// piece of mutable state (page table, free list, replacer, etc.) lives in
+/// `BPMFields` behind `fields`'s mutex, and page storage lives in
+/// `pages_concurrent`, where each slot carries its own `RwLock`. There is no
+/// separate single-threaded copy of this state to keep in sync.
+pub struct BufferPoolManager {
     // log_manager,
     disk_manager: DiskManager,
-    page_table: HashMap<PageID, FrameID>,
-    meta_data: HashMap<FrameID, MetaData>,
-    replacer: LRUKReplacer,
-    free_list: LinkedList<FrameID>,
-    pages: Vec<Page>,
-
     pages_concurrent: Vec<Arc<RwLock<Page>>>,
     fields: Mutex<BPMFields>,
 }
 
 impl BufferPoolManager {
     pub fn new(pool_size: usize, disk_manager: DiskManager, replacer_k: usize) -> Self {
+        let (next_page_id, free_page_ids) = decode_free_page_list(&disk_manager);
+        // A freshly formatted disk decodes to an all-zero header; ids up to
+        // `FIRST_DATA_PAGE_ID` are reserved for free-list bookkeeping.
+        let next_page_id = if next_page_id == 0 && free_page_ids.is_empty() { FIRST_DATA_PAGE_ID } else { next_page_id };
+
         let mut this = Self {
-            pool_size,
-            next_page_id: 0,
             disk_manager,
-            page_table: HashMap::new(),
-            meta_data: HashMap::new(),
-            replacer: LRUKReplacer::new(pool_size, replacer_k),
-            free_list: LinkedList::new(),
-            pages: vec![Page::new(); pool_size],
             pages_concurrent: Vec::new(),
-            fields: Mutex::new(BPMFields::default()),
+            fields: Mutex::new(BPMFields {
+                next_page_id,
+                page_table: ExtendibleHashTable::new(PAGE_TABLE_BUCKET_CAPACITY),
+                meta_data: HashMap::new(),
+                replacer: LRUKReplacer::new(pool_size, replacer_k),
+                free_list: LinkedList::new(),
+                free_page_ids,
+                snapshots: SnapshotManager::default(),
+            }),
         };
-        
-        for i in 0..pool_size {
-            this.free_list.push_back(i as FrameID);
+
+        for _ in 0..pool_size {
             this.pages_concurrent.push(Arc::new(RwLock::new(Page::new())));
+            this.fields.get_mut().expect("fields lock failed").free_list.push_back(this.pages_concurrent.len() as FrameID - 1);
         }
 
         return this;
     }
 
-    /**
-     * @brief Create a new page in the buffer pool. Set page_id to the new page's id, or nullptr if all frames
-     * are currently in use and not evictable (in another word, pinned).
-     *
-     * You should pick the replacement frame from either the free list or the replacer (always find from the free list
-     * first), and then call the AllocatePage() method to get a new page id. If the replacement frame has a dirty page,
-     * you should write it back to the disk first. You also need to reset the memory and metadata for the new page.
-     *
-     * Remember to "Pin" the frame by calling replacer.SetEvictable(frame_id, false)
-     * so that the replacer wouldn't evict the frame before the buffer pool manager "Unpin"s it.
-     * Also, remember to record the access history of the frame in the replacer for the lru-k algorithm to work.
-     *
-     * @param[out] page_id id of created page
-     * @return nullptr if no new pages could be created, otherwise pointer to new page
-     */
-    pub fn new_page(&mut self, page_id: &mut PageID) -> Option<&mut Page> {
-        let mut frame_id: FrameID = -1;
+    /// Writes the current `next_page_id`/free-page-id state back to the
+    /// reserved header page, chaining onto the reserved free-list pages if it
+    /// no longer fits on the header page alone, so it survives a restart.
+    fn persist_free_page_list(&self, next_page_id: PageID, free_page_ids: &LinkedList<PageID>) {
+        encode_free_page_list(&self.disk_manager, next_page_id, free_page_ids);
+    }
 
-        if !self.free_list.is_empty() {
-            // if free frames exist
-            frame_id = *self.free_list.front().unwrap();
-            self.free_list.pop_front();
-        } else {
-            // all frames are occupied, need eviction
-            if !self.replacer.evict(&mut frame_id) {
-                return None;
-            }
-            let evicted_page = &self.pages[frame_id as usize];
-            if evicted_page.is_dirty {
-                self.disk_manager.write_page(evicted_page.page_id, &evicted_page.data);
-            }
-            self.page_table.remove(&evicted_page.page_id);
+    /// Creates a new page in the buffer pool. Picks a replacement frame from
+    /// the free list first, then the replacer, writing back a dirty victim
+    /// before reusing its frame. The whole `BPMFields` bundle
+    /// (free_list, page_table, meta_data, replacer, next_page_id) lives behind
+    /// `self.fields`'s mutex; the actual page storage is `pages_concurrent`,
+    /// which needs no lock of its own since each slot carries its own
+    /// `RwLock` and the vector itself never resizes after construction. The
+    /// fields lock is dropped before any disk I/O so other threads can keep
+    /// making progress while a dirty victim is written back.
+    pub fn new_page_concurrent(&self, page_id: &mut PageID) -> Option<Arc<RwLock<Page>>> {
+        let fields = self.fields.lock().expect("fields lock failed");
+        let (frame_id, mut fields) = self.evict_frame_concurrent(fields);
+        let frame_id = frame_id?;
+
+        *page_id = fields.allocate_page();
+        fields.page_table.insert(*page_id, frame_id);
+        fields.replacer.record_access(frame_id);
+        fields.replacer.set_evictable(frame_id, false);
+        fields.meta_data.insert(frame_id, MetaData { page_id: *page_id, pin_count: 1, is_dirty: false });
+        let next_page_id = fields.next_page_id;
+        let free_page_ids = fields.free_page_ids.clone();
+        drop(fields);
+        self.persist_free_page_list(next_page_id, &free_page_ids);
+
+        let page_ptr = self.pages_concurrent[frame_id as usize].clone();
+        {
+            let mut page = page_ptr.write().expect("page wlock failed");
+            page.reset_memory();
+            page.page_id = *page_id;
+            page.is_dirty = false;
+            page.pin_count = 1;
         }
 
-        *page_id = self.allocate_page();
-        self.page_table.insert(*page_id, frame_id);
-
-        self.replacer.record_access(frame_id);
-        self.replacer.set_evictable(frame_id, false);
-
-        self.pages[frame_id as usize].pin_count = 1;
-        self.pages[frame_id as usize].page_id = *page_id;
-
-        return Some(&mut self.pages[frame_id as usize]);
+        Some(page_ptr)
     }
 
-    pub fn new_page_concurrent(&mut self, page_id: &mut PageID) -> Option<&mut Page> {
+    /// Fetches `page_id` into the buffer pool, reading it from disk if it
+    /// isn't already resident. See `new_page_concurrent` for the locking
+    /// discipline this and the rest of the `_concurrent` family share.
+    pub fn fetch_page_concurrent(&self, page_id: PageID) -> Option<Arc<RwLock<Page>>> {
         let mut fields = self.fields.lock().expect("fields lock failed");
 
-        let mut frame_id: FrameID = -1;
+        if let Some(frame_id) = fields.page_table.find(&page_id) {
+            fields.replacer.record_access(frame_id);
+            fields.replacer.set_evictable(frame_id, false);
+            fields.meta_data.get_mut(&frame_id).unwrap().pin_count += 1;
+            return Some(self.pages_concurrent[frame_id as usize].clone());
+        }
 
-        if !fields.free_list.is_empty() {
-            // if free frames exist
-            frame_id = *fields.free_list.front().unwrap();
-            fields.free_list.pop_front();
-        } else {
-            // all frames are occupied, need eviction
-            if !fields.replacer.evict(&mut frame_id) {
-                return None;
-            }
-            let evicted_page_ptr = fields.pages[frame_id as usize].clone();
-            if fields.meta_data[&frame_id].is_dirty {
-                drop(fields);
-                let evicted_page = evicted_page_ptr.read().expect("evicted page rLock failed");
-                self.disk_manager.write_page(evicted_page.page_id, &evicted_page.data);
-                fields = self.fields.lock().expect("fields lock failed");
-            }
-            let id = fields.meta_data[&frame_id].page_id;
-            fields.page_table.remove(&id);
+        let (frame_id, fields) = self.evict_frame_concurrent(fields);
+        let frame_id = frame_id?;
+        drop(fields);
+
+        let page_ptr = self.pages_concurrent[frame_id as usize].clone();
+        {
+            let mut page = page_ptr.write().expect("page wlock failed");
+            self.disk_manager.read_page(page_id, &mut page.data);
+            page.page_id = page_id;
+            page.is_dirty = false;
         }
 
-        // *page_id = self.allocate_page();
-        fields.page_table.insert(*page_id, frame_id);
+        let mut fields = self.fields.lock().expect("fields lock failed");
+        if let Some(winner_frame_id) = fields.page_table.find(&page_id) {
+            // Another thread raced us while the lock was dropped for the disk
+            // read above, fetched the same missing page_id first, and already
+            // won the page_table slot. Pin its frame instead of clobbering the
+            // entry with ours, and give the frame we just loaded into back to
+            // the free list instead of leaking it.
+            fields.replacer.record_access(winner_frame_id);
+            fields.replacer.set_evictable(winner_frame_id, false);
+            fields.meta_data.get_mut(&winner_frame_id).unwrap().pin_count += 1;
+            fields.free_list.push_back(frame_id);
+            return Some(self.pages_concurrent[winner_frame_id as usize].clone());
+        }
 
+        fields.page_table.insert(page_id, frame_id);
         fields.replacer.record_access(frame_id);
         fields.replacer.set_evictable(frame_id, false);
+        fields.meta_data.insert(frame_id, MetaData { page_id, pin_count: 1, is_dirty: false });
 
-        fields.meta_data.get_mut(&frame_id).unwrap().pin_count = 1;
-        fields.meta_data.get_mut(&frame_id).unwrap().page_id = *page_id;
-
-        return Some(&mut self.pages[frame_id as usize]);
+        Some(page_ptr)
     }
 
-    /**
-     * @brief Fetch the requested page from the buffer pool. Return nullptr if page_id needs to be fetched from the disk
-     * but all frames are currently in use and not evictable (in another word, pinned).
-     *
-     * First search for page_id in the buffer pool. If not found, pick a replacement frame from either the free list or
-     * the replacer (always find from the free list first), read the page from disk by calling disk_manager_->ReadPage(),
-     * and replace the old page in the frame. Similar to NewPage(), if the old page is dirty, you need to write it back
-     * to disk and update the metadata of the new page
-     *
-     * In addition, remember to disable eviction and record the access history of the frame like you did for NewPage().
-     *
-     * @param page_id id of page to be fetched
-     * @return nullptr if page_id cannot be fetched, otherwise pointer to the requested page
-     */
-    pub fn fetch_page(&mut self, page_id: PageID) -> Option<&mut Page> {
-        let mut frame_id: FrameID = -1;
+    /// Decrements `page_id`'s pin count, making its frame evictable again
+    /// once the count reaches zero.
+    pub fn unpin_page_concurrent(&self, page_id: PageID, is_dirty: bool) -> bool {
+        let mut fields = self.fields.lock().expect("fields lock failed");
 
-        // if page is already in the buffer pool
-        if self.page_table.contains_key(&page_id) {
-            frame_id = self.page_table[&page_id];
-            self.replacer.record_access(frame_id);
-            self.replacer.set_evictable(frame_id, false);
-            self.pages[frame_id as usize].pin_count += 1;
-            return Some(&mut self.pages[frame_id as usize])
+        let Some(frame_id) = fields.page_table.find(&page_id) else {
+            return false;
+        };
+        let meta = fields.meta_data.get_mut(&frame_id).unwrap();
+        if meta.pin_count == 0 {
+            return false;
         }
 
-        // page not buffered, need to read page
-        if !self.free_list.is_empty() {
-            // if free frames exist
-            frame_id = *self.free_list.front().unwrap();
-            self.free_list.pop_front();
-        } else {
-            // all frames are occupied, need eviction
-            if !self.replacer.evict(&mut frame_id) {
-                return None;
-            }
-            let evicted_page = &self.pages[frame_id as usize];
-            if evicted_page.is_dirty {
-                self.disk_manager.write_page(evicted_page.page_id, &evicted_page.data);
-            }
-            self.page_table.remove(&evicted_page.page_id);
+        meta.is_dirty = is_dirty;
+        meta.pin_count -= 1;
+
+        if meta.pin_count == 0 {
+            fields.replacer.set_evictable(frame_id, true);
         }
 
-        self.disk_manager.read_page(page_id, &mut self.pages[frame_id as usize].data);
-        self.page_table.insert(page_id, frame_id);
+        true
+    }
 
-        self.replacer.record_access(frame_id);
-        self.replacer.set_evictable(frame_id, false);
+    /// Writes `page_id` to disk regardless of its dirty flag, then clears it.
+    pub fn flush_page_concurrent(&self, page_id: PageID) -> bool {
+        let frame_id = {
+            let fields = self.fields.lock().expect("fields lock failed");
+            match fields.page_table.find(&page_id) {
+                Some(frame_id) => frame_id,
+                None => return false,
+            }
+        };
 
-        self.pages[frame_id as usize].pin_count = 1;
-        self.pages[frame_id as usize].page_id = page_id;
+        let mut page = self.pages_concurrent[frame_id as usize].write().expect("page wlock failed");
+        self.disk_manager.write_page(page_id, &page.data);
+        page.is_dirty = false;
 
-        return Some(&mut self.pages[frame_id as usize]);
+        true
     }
 
-    /**
-     * @brief Unpin the target page from the buffer pool. If page_id is not in the buffer pool or its pin count is already
-     * 0, return false.
-     *
-     * Decrement the pin count of a page. If the pin count reaches 0, the frame should be evictable by the replacer.
-     * Also, set the dirty flag on the page to indicate if the page was modified.
-     *
-     * @param page_id id of page to be unpinned
-     * @param is_dirty true if the page should be marked as dirty, false otherwise
-     * @return false if the page is not in the page table or its pin count is <= 0 before this call, true otherwise
-     */
-    pub fn unpin_page(&mut self, page_id: PageID, is_dirty: bool) -> bool {
-        if !self.page_table.contains_key(&page_id) {
+    /// Deletes `page_id` from the buffer pool, refusing if it's still pinned.
+    /// After removing it from the page table, stops tracking its frame in
+    /// the replacer, returns the frame to the free list, resets the page's
+    /// memory and metadata, and calls `deallocate_page` to free the id.
+    pub fn delete_page_concurrent(&self, page_id: PageID) -> bool {
+        let mut fields = self.fields.lock().expect("fields lock failed");
+
+        let Some(frame_id) = fields.page_table.find(&page_id) else {
+            return true;
+        };
+        if fields.meta_data[&frame_id].pin_count > 0 {
             return false;
         }
 
-        let frame_id: FrameID = self.page_table[&page_id];
-        if self.pages[frame_id as usize].pin_count == 0 {
-            return false;
+        fields.page_table.remove(&page_id);
+        fields.replacer.remove(frame_id);
+        fields.free_list.push_back(frame_id);
+        fields.meta_data.remove(&frame_id);
+        fields.deallocate_page(page_id);
+        let next_page_id = fields.next_page_id;
+        let free_page_ids = fields.free_page_ids.clone();
+        drop(fields);
+        self.persist_free_page_list(next_page_id, &free_page_ids);
+
+        let mut page = self.pages_concurrent[frame_id as usize].write().expect("page wlock failed");
+        page.page_id = INVALID_PAGE_ID;
+        page.is_dirty = false;
+        page.pin_count = 0;
+        page.reset_memory();
+        drop(page);
+
+        true
+    }
+
+    /// Shared free-list-then-replacer victim selection for the `_concurrent`
+    /// family: pops a frame from the free list if one is available, otherwise
+    /// asks the replacer to evict one. A dirty victim is written back to disk
+    /// with the fields lock dropped, then the lock is re-acquired and handed
+    /// back so the caller can keep using it. Returns `None` (with the lock
+    /// still held) if no frame can be reclaimed.
+    fn evict_frame_concurrent<'a>(
+        &'a self,
+        mut fields: MutexGuard<'a, BPMFields>,
+    ) -> (Option<FrameID>, MutexGuard<'a, BPMFields>) {
+        if let Some(&frame_id) = fields.free_list.front() {
+            fields.free_list.pop_front();
+            return (Some(frame_id), fields);
         }
 
-        self.pages[frame_id as usize].is_dirty = is_dirty;
-        self.pages[frame_id as usize].pin_count -= 1;
+        let mut frame_id: FrameID = -1;
+        if !fields.replacer.evict(&mut frame_id) {
+            return (None, fields);
+        }
 
-        if self.pages[frame_id as usize].pin_count == 0 {
-            self.replacer.set_evictable(frame_id, true);
+        // Remove the victim's page_table entry before the lock is ever
+        // dropped for the writeback below: otherwise a concurrent
+        // `fetch_page_concurrent` for the evicted page id can still find the
+        // mapping, re-pin this very frame, and race with the caller about to
+        // overwrite it.
+        let evicted_page_id = fields.meta_data[&frame_id].page_id;
+        fields.page_table.remove(&evicted_page_id);
+
+        if fields.meta_data[&frame_id].is_dirty {
+            let evicted_page_ptr = self.pages_concurrent[frame_id as usize].clone();
+            drop(fields);
+            let evicted_page = evicted_page_ptr.read().expect("evicted page rlock failed");
+            self.disk_manager.write_page(evicted_page.page_id, &evicted_page.data);
+            drop(evicted_page);
+            fields = self.fields.lock().expect("fields lock failed");
         }
 
-        return true;
+        (Some(frame_id), fields)
     }
 
-    /**
-     * @brief Flush the target page to disk.
-     *
-     * Use the DiskManager::WritePage() method to flush a page to disk, REGARDLESS of the dirty flag.
-     * Unset the dirty flag of the page after flushing.
-     *
-     * @param page_id id of page to be flushed, cannot be INVALID_PAGE_ID
-     * @return false if the page could not be found in the page table, true otherwise
-     */
-    pub fn flush_page(&mut self, page_id: PageID) -> bool {
-        if !self.page_table.contains_key(&page_id) {
-            return false;
+    /// Fetches `page_id` into the concurrent buffer pool and returns a
+    /// `BasicPageGuard` that auto-unpins on drop, instead of a raw page
+    /// reference the caller must remember to `unpin_page` manually.
+    pub fn fetch_page_basic(self: &Arc<Self>, page_id: PageID) -> Option<BasicPageGuard> {
+        let page = self.fetch_page_concurrent(page_id)?;
+        let frame_id = self.fields.lock().expect("fields lock failed").page_table.find(&page_id).unwrap();
+        Some(BasicPageGuard::new(self.clone(), page_id, frame_id, page))
+    }
+
+    /// Like `fetch_page_basic`, but upgrades straight to a `ReadPageGuard`
+    /// holding the page's read lock.
+    pub fn fetch_page_read(self: &Arc<Self>, page_id: PageID) -> Option<ReadPageGuard> {
+        self.fetch_page_basic(page_id).map(BasicPageGuard::upgrade_read)
+    }
+
+    /// Like `fetch_page_basic`, but upgrades straight to a `WritePageGuard`
+    /// holding the page's write lock and marking it dirty. If `page_id` has
+    /// readers pinned to an earlier snapshot, copies the live page into a
+    /// fresh frame first (see `cow_if_needed`) so they keep seeing the old
+    /// contents while this guard writes through the new one.
+    ///
+    /// The initial `fetch_page_concurrent` keeps `page_id` pinned for the
+    /// whole call, so its current frame can't be chosen as the COW swap's own
+    /// destination out from under it (eviction never picks a pinned frame).
+    /// That pin is then either handed off (if no swap happened, it becomes
+    /// the guard's pin once `fetch_page_basic` adds its own and we release
+    /// the extra one) or simply left behind (if a swap happened, the old
+    /// frame's metadata — and with it this pin — was removed by `cow_if_needed`,
+    /// so there's nothing left to release).
+    pub fn fetch_page_write(self: &Arc<Self>, page_id: PageID) -> Option<WritePageGuard> {
+        self.fetch_page_concurrent(page_id)?;
+        let cow_happened = self.cow_if_needed(page_id);
+        let Some(guard) = self.fetch_page_basic(page_id) else {
+            // The initial fetch above is only left unreleased when a COW swap
+            // happened (its frame's metadata, and with it that pin, was
+            // removed by `cow_if_needed`); otherwise it's still outstanding
+            // and must be released here, or it leaks permanently.
+            if !cow_happened {
+                self.unpin_page_concurrent(page_id, false);
+            }
+            return None;
+        };
+        if !cow_happened {
+            self.unpin_page_concurrent(page_id, false);
         }
+        Some(guard.upgrade_write())
+    }
 
-        let frame_id: FrameID = self.page_table[&page_id];
-        self.disk_manager.write_page(page_id, &mut self.pages[frame_id as usize].data);
-        self.pages[frame_id as usize].is_dirty = false;
+    /// Registers a new point-in-time snapshot and returns its id, for use
+    /// with `fetch_page_snapshot`. Every page is implicitly visible to it as
+    /// of this moment, until a writer copies one out from under it.
+    pub fn begin_snapshot(&self) -> SnapshotID {
+        self.fields.lock().expect("fields lock failed").snapshots.begin_snapshot()
+    }
 
-        return true;
+    /// Ends `snapshot_id`. Any copy-on-write version it was the last reader
+    /// of is returned to the free list.
+    pub fn release_snapshot(&self, snapshot_id: SnapshotID) {
+        let mut fields = self.fields.lock().expect("fields lock failed");
+        let freed_frames = fields.snapshots.release_snapshot(snapshot_id);
+        for frame_id in freed_frames {
+            fields.free_list.push_back(frame_id);
+        }
     }
 
-    /**
-     * @brief Flush all the pages in the buffer pool to disk.
-     */
-    pub fn flush_all_pages(&mut self) {
-        for i in 0..self.pool_size {
-            let page = &mut self.pages[i];
-            if page.page_id != INVALID_PAGE_ID {
-                if !self.page_table.contains_key(&page.page_id) {
-                    continue;
-                }
-                let frame_id: FrameID = self.page_table[&page.page_id];
-                self.disk_manager.write_page(page.page_id, &mut self.pages[frame_id as usize].data);
-                self.pages[frame_id as usize].is_dirty = false;
-            }
+    /// Fetches the version of `page_id` that `snapshot_id` is entitled to
+    /// see. If a writer has copy-on-written the page since the snapshot was
+    /// opened, returns the retained old version, pinned by the snapshot's own
+    /// reference count; release it by calling `release_snapshot`, not
+    /// `unpin_page_concurrent`. Otherwise falls back to the live page via
+    /// `fetch_page_concurrent`, which pins it normally and must be released
+    /// the normal way.
+    pub fn fetch_page_snapshot(&self, page_id: PageID, snapshot_id: SnapshotID) -> Option<Arc<RwLock<Page>>> {
+        let mut fields = self.fields.lock().expect("fields lock failed");
+        if let Some(page) = fields.snapshots.find_version(page_id, snapshot_id) {
+            return Some(page);
         }
+        drop(fields);
+        self.fetch_page_concurrent(page_id)
     }
 
-    /**
-     * @brief Delete a page from the buffer pool. If page_id is not in the buffer pool, do nothing and return true. If the
-     * page is pinned and cannot be deleted, return false immediately.
-     *
-     * After deleting the page from the page table, stop tracking the frame in the replacer and add the frame
-     * back to the free list. Also, reset the page's memory and metadata. Finally, you should call DeallocatePage() to
-     * imitate freeing the page on the disk.
-     *
-     * @param page_id id of page to be deleted
-     * @return false if the page exists but could not be deleted, true if the page didn't exist or deletion succeeded
-     */
-    pub fn delete_page(&mut self, page_id: PageID) -> bool {
-        if !self.page_table.contains_key(&page_id) {
+    /// Copies `page_id`'s live contents into a freshly evicted frame and
+    /// redirects the page table to it, retaining the old frame in the
+    /// snapshot manager's version chain, if any open snapshot hasn't yet been
+    /// given a frozen version of this page (see `SnapshotManager::needs_cow`).
+    /// A no-op (returning `false`) when there are no snapshots that need
+    /// protecting. Callers must keep `page_id` pinned across this call (see
+    /// `fetch_page_write`), so the source frame can never be the one handed
+    /// back by `evict_frame_concurrent`.
+    fn cow_if_needed(&self, page_id: PageID) -> bool {
+        let fields = self.fields.lock().expect("fields lock failed");
+        if !fields.snapshots.needs_cow(page_id) {
             return false;
         }
+        let Some(old_frame_id) = fields.page_table.find(&page_id) else {
+            return false;
+        };
 
-        let frame_id: FrameID = self.page_table[&page_id];
-        if self.pages[frame_id as usize].pin_count > 0 {
+        let (new_frame_id, mut fields) = self.evict_frame_concurrent(fields);
+        let Some(new_frame_id) = new_frame_id else {
             return false;
+        };
+        if new_frame_id == old_frame_id {
+            // Should be unreachable: the caller keeps `page_id` pinned for
+            // the duration of this call, so `old_frame_id` can't be in the
+            // free list or evictable. Bail rather than copy a frame onto
+            // itself if that invariant is ever violated.
+            return false;
+        }
+        drop(fields);
+
+        let old_page_ptr = self.pages_concurrent[old_frame_id as usize].clone();
+        let new_page_ptr = self.pages_concurrent[new_frame_id as usize].clone();
+        {
+            let old_page = old_page_ptr.read().expect("old page rlock poisoned");
+            let mut new_page = new_page_ptr.write().expect("new page wlock poisoned");
+            *new_page = old_page.clone();
+            new_page.pin_count = 0;
         }
 
-        self.page_table.remove(&page_id);
-        self.replacer.remove(frame_id);
-        self.free_list.push_back(frame_id);
-        let page = &mut self.pages[frame_id as usize];
-        page.page_id = INVALID_PAGE_ID;
-        page.is_dirty = false;
-        page.pin_count = 0;
-        page.reset_memory();
-        self.deallocate_page();
+        let mut fields = self.fields.lock().expect("fields lock failed");
+        fields.page_table.insert(page_id, new_frame_id);
+        fields.meta_data.insert(new_frame_id, MetaData { page_id, pin_count: 0, is_dirty: false });
+        fields.replacer.remove(old_frame_id);
+        fields.meta_data.remove(&old_frame_id);
+        fields.snapshots.freeze_version(page_id, old_frame_id, old_page_ptr);
+        true
+    }
 
-        return true;
+    /// Shared pin-release bookkeeping for the RAII page guards: decrements
+    /// the frame's pin count and, once it reaches zero, hands the frame back
+    /// to the replacer as an eviction candidate. Like `unpin_page_concurrent`
+    /// but keyed by frame, and an `is_dirty` of `false` leaves an existing
+    /// dirty flag set instead of clearing it.
+    pub(super) fn unpin_frame(&self, frame_id: FrameID, is_dirty: bool) {
+        let mut fields = self.fields.lock().expect("fields lock failed");
+        if let Some(meta) = fields.meta_data.get_mut(&frame_id) {
+            if is_dirty {
+                meta.is_dirty = true;
+            }
+            if meta.pin_count > 0 {
+                meta.pin_count -= 1;
+            }
+            if meta.pin_count == 0 {
+                fields.replacer.set_evictable(frame_id, true);
+            }
+        }
     }
+}
 
-    fn allocate_page(&mut self) -> PageID {
-        self.next_page_id += 1;
-        return self.next_page_id - 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_list_suffix_round_trips() {
+        let ids: LinkedList<PageID> = [10, 11, 12].into_iter().collect();
+        let mut buf = Page::new();
+        encode_free_list_suffix(&mut buf.data, 0, 99, &ids.iter().copied().collect::<Vec<_>>());
+
+        let (chain_next, decoded) = decode_free_list_suffix(&buf.data, 0);
+        assert_eq!(chain_next, 99);
+        assert_eq!(decoded, vec![10, 11, 12]);
     }
 
-    fn deallocate_page(&mut self) {
-        
+    #[test]
+    fn free_list_suffix_fits_exactly_the_page_capacity() {
+        let buf = Page::new();
+        let id_size = std::mem::size_of::<PageID>();
+        let capacity = free_ids_capacity(buf.data.len(), id_size + 8);
+        let ids: Vec<PageID> = (0..capacity as PageID).collect();
+
+        let mut buf = Page::new();
+        encode_free_list_suffix(&mut buf.data, 0, FREE_LIST_CHAIN_END, &ids);
+        let (_, decoded) = decode_free_list_suffix(&buf.data, 0);
+        assert_eq!(decoded, ids);
+    }
+
+    #[test]
+    fn decode_caps_a_corrupted_count_to_the_page_capacity_instead_of_panicking() {
+        let buf = Page::new();
+        let id_size = std::mem::size_of::<PageID>();
+        let capacity = free_ids_capacity(buf.data.len(), id_size + 8);
+
+        let mut buf = Page::new();
+        // Encode a real, in-bounds list, then corrupt the stored count to
+        // claim far more ids than the page can possibly hold.
+        encode_free_list_suffix(&mut buf.data, 0, FREE_LIST_CHAIN_END, &[1, 2, 3]);
+        buf.data[id_size..id_size + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let (_, decoded) = decode_free_list_suffix(&buf.data, 0);
+        assert_eq!(decoded.len(), capacity);
     }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::common::{FrameID, PageID};
+use crate::storage::page::page::Page;
+
+use super::buffer_pool_manager::BufferPoolManager;
+
+/// An RAII handle on a pinned page that has not yet committed to read or write
+/// access. Dropping it (or calling `drop()` explicitly) unpins the page;
+/// `upgrade_read`/`upgrade_write` hand the pin off to a locked guard instead.
+pub struct BasicPageGuard {
+    bpm: Arc<BufferPoolManager>,
+    page: Arc<RwLock<Page>>,
+    page_id: PageID,
+    frame_id: FrameID,
+    is_dirty: bool,
+    released: bool,
+}
+
+impl BasicPageGuard {
+    pub(super) fn new(
+        bpm: Arc<BufferPoolManager>,
+        page_id: PageID,
+        frame_id: FrameID,
+        page: Arc<RwLock<Page>>,
+    ) -> Self {
+        Self { bpm, page, page_id, frame_id, is_dirty: false, released: false }
+    }
+
+    pub fn page_id(&self) -> PageID {
+        self.page_id
+    }
+
+    /// Marks the underlying page dirty so it is written back on eviction,
+    /// without needing a write lock.
+    pub fn mark_dirty(&mut self) {
+        self.is_dirty = true;
+    }
+
+    /// Consumes this guard and returns a `ReadPageGuard` holding the same pin,
+    /// taking the page's read lock.
+    pub fn upgrade_read(mut self) -> ReadPageGuard {
+        self.released = true;
+        ReadPageGuard::new(self.bpm.clone(), self.page_id, self.frame_id, self.page.clone())
+    }
+
+    /// Consumes this guard and returns a `WritePageGuard` holding the same
+    /// pin, taking the page's write lock and marking it dirty.
+    pub fn upgrade_write(mut self) -> WritePageGuard {
+        self.released = true;
+        WritePageGuard::new(self.bpm.clone(), self.page_id, self.frame_id, self.page.clone())
+    }
+
+    /// Releases the pin early instead of waiting for the guard to go out of scope.
+    #[allow(clippy::should_implement_trait)]
+    pub fn drop(mut self) {
+        self.released = true;
+        self.bpm.unpin_frame(self.frame_id, self.is_dirty);
+    }
+}
+
+impl Drop for BasicPageGuard {
+    fn drop(&mut self) {
+        if !self.released {
+            self.released = true;
+            self.bpm.unpin_frame(self.frame_id, self.is_dirty);
+        }
+    }
+}
+
+/// An RAII handle holding a page's read lock for as long as the guard lives.
+/// Unpins the page on drop, like `BasicPageGuard`.
+pub struct ReadPageGuard {
+    // SAFETY: borrows from the `RwLock` owned by `page` below, transmuted to
+    // 'static. Struct fields drop in declaration order, so `lock` must be
+    // declared (and thus dropped) before `page` and `bpm`: `page` is this
+    // guard's own strong reference to the `RwLock`, and it's the only thing
+    // guaranteed to outlive `lock` if `bpm` happens to be the last other
+    // `Arc<BufferPoolManager>` alive (which would otherwise drop
+    // `pages_concurrent`'s clone first). Never reorder these fields.
+    lock: RwLockReadGuard<'static, Page>,
+    page: Arc<RwLock<Page>>,
+    bpm: Arc<BufferPoolManager>,
+    page_id: PageID,
+    frame_id: FrameID,
+    released: bool,
+}
+
+impl ReadPageGuard {
+    pub(super) fn new(bpm: Arc<BufferPoolManager>, page_id: PageID, frame_id: FrameID, page: Arc<RwLock<Page>>) -> Self {
+        let lock: RwLockReadGuard<'static, Page> =
+            unsafe { std::mem::transmute(page.read().expect("page rlock poisoned")) };
+        Self { lock, page, bpm, page_id, frame_id, released: false }
+    }
+
+    pub fn page_id(&self) -> PageID {
+        self.page_id
+    }
+
+    /// Releases the pin early instead of waiting for the guard to go out of scope.
+    #[allow(clippy::should_implement_trait)]
+    pub fn drop(mut self) {
+        self.released = true;
+        self.bpm.unpin_frame(self.frame_id, false);
+    }
+}
+
+impl Deref for ReadPageGuard {
+    type Target = Page;
+
+    fn deref(&self) -> &Page {
+        &self.lock
+    }
+}
+
+impl Drop for ReadPageGuard {
+    fn drop(&mut self) {
+        if !self.released {
+            self.released = true;
+            self.bpm.unpin_frame(self.frame_id, false);
+        }
+    }
+}
+
+/// An RAII handle holding a page's write lock for as long as the guard lives.
+/// Marks the page dirty on construction and unpins it on drop.
+pub struct WritePageGuard {
+    // SAFETY: same reasoning as `ReadPageGuard::lock` above — must stay
+    // declared before `page`/`bpm` so it drops first.
+    lock: RwLockWriteGuard<'static, Page>,
+    page: Arc<RwLock<Page>>,
+    bpm: Arc<BufferPoolManager>,
+    page_id: PageID,
+    frame_id: FrameID,
+    released: bool,
+}
+
+impl WritePageGuard {
+    pub(super) fn new(bpm: Arc<BufferPoolManager>, page_id: PageID, frame_id: FrameID, page: Arc<RwLock<Page>>) -> Self {
+        let mut lock: RwLockWriteGuard<'static, Page> =
+            unsafe { std::mem::transmute(page.write().expect("page wlock poisoned")) };
+        lock.is_dirty = true;
+        Self { lock, page, bpm, page_id, frame_id, released: false }
+    }
+
+    pub fn page_id(&self) -> PageID {
+        self.page_id
+    }
+
+    /// Releases the pin early instead of waiting for the guard to go out of scope.
+    #[allow(clippy::should_implement_trait)]
+    pub fn drop(mut self) {
+        self.released = true;
+        self.bpm.unpin_frame(self.frame_id, true);
+    }
+}
+
+impl Deref for WritePageGuard {
+    type Target = Page;
+
+    fn deref(&self) -> &Page {
+        &self.lock
+    }
+}
+
+impl DerefMut for WritePageGuard {
+    fn deref_mut(&mut self) -> &mut Page {
+        &mut self.lock
+    }
+}
+
+impl Drop for WritePageGuard {
+    fn drop(&mut self) {
+        if !self.released {
+            self.released = true;
+            self.bpm.unpin_frame(self.frame_id, true);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::common::{FrameID, PageID};
+use crate::storage::page::page::Page;
+
+/// Identifies a point-in-time view registered with `BufferPoolManager::begin_snapshot`.
+/// Ids are handed out from a monotonically increasing counter, so "is this
+/// snapshot older than that copy-on-write event" reduces to integer comparison.
+pub type SnapshotID = u64;
+
+/// One retained old copy of a page: the frame it lives in, the snapshot
+/// counter value at the moment it was frozen (any snapshot opened before that
+/// moment is entitled to see it), and a count of how many of those snapshots
+/// are still open. `ref_count` is seeded once at freeze time from exactly the
+/// snapshots it covers and decremented exactly once per covered snapshot when
+/// that snapshot is released — never touched by `find_version`, so reading a
+/// version (zero, one, or many times) cannot over- or under-count it.
+struct VersionEntry {
+    valid_before: SnapshotID,
+    frame_id: FrameID,
+    page: Arc<RwLock<Page>>,
+    ref_count: usize,
+}
+
+/// The chain of retained versions for one page, oldest first.
+#[derive(Default)]
+struct VersionChain {
+    versions: Vec<VersionEntry>,
+}
+
+/// Tracks open snapshots and the copy-on-write page versions they pin, so a
+/// long-running reader can see a consistent view of a page while writers keep
+/// modifying the live copy. Meant to live behind the buffer pool manager's
+/// existing fields mutex rather than its own lock, since every operation here
+/// also needs to touch the page table / replacer / free list in the same
+/// critical section.
+#[derive(Default)]
+pub struct SnapshotManager {
+    next_snapshot_id: SnapshotID,
+    active: HashSet<SnapshotID>,
+    chains: HashMap<PageID, VersionChain>,
+}
+
+impl SnapshotManager {
+    /// Registers a new snapshot and returns its id. Every page version still
+    /// live at this moment is implicitly visible to it until a writer copies
+    /// a page out from under it.
+    pub fn begin_snapshot(&mut self) -> SnapshotID {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.active.insert(id);
+        id
+    }
+
+    /// Whether a writer about to touch `page_id` must copy-on-write first:
+    /// true if some open snapshot hasn't yet been given a frozen version that
+    /// covers it.
+    pub fn needs_cow(&self, page_id: PageID) -> bool {
+        if self.active.is_empty() {
+            return false;
+        }
+        match self.chains.get(&page_id).and_then(|chain| chain.versions.last()) {
+            None => true,
+            Some(latest) => self.active.iter().any(|&id| id >= latest.valid_before),
+        }
+    }
+
+    /// Freezes `page`'s current contents (held in `frame_id`) as a new
+    /// version of `page_id`. Pinned, up front, by every snapshot currently
+    /// open — each of them was opened before this moment, so each is entitled
+    /// to see this version once the live page moves on. Called by the writer
+    /// right after it has redirected the page table to a fresh frame, so the
+    /// old frame/version can keep living on for readers.
+    pub fn freeze_version(&mut self, page_id: PageID, frame_id: FrameID, page: Arc<RwLock<Page>>) {
+        if self.active.is_empty() {
+            return;
+        }
+        self.chains.entry(page_id).or_default().versions.push(VersionEntry {
+            valid_before: self.next_snapshot_id,
+            frame_id,
+            page,
+            ref_count: self.active.len(),
+        });
+    }
+
+    /// Looks up the version of `page_id` that `snapshot_id` is entitled to
+    /// see, if it differs from the live page (i.e. a write has happened since
+    /// the snapshot was opened). Read-only: the version was already pinned on
+    /// `snapshot_id`'s behalf back in `freeze_version`, so looking it up here
+    /// (even repeatedly) does not change its reference count. Returns `None`
+    /// when no write has happened yet, meaning the caller should read the
+    /// live page instead.
+    pub fn find_version(&self, page_id: PageID, snapshot_id: SnapshotID) -> Option<Arc<RwLock<Page>>> {
+        let chain = self.chains.get(&page_id)?;
+        let entry = chain.versions.iter().find(|entry| snapshot_id < entry.valid_before)?;
+        Some(entry.page.clone())
+    }
+
+    /// Ends `snapshot_id`, releasing its pin on every version it was counted
+    /// in at freeze time (every version frozen while it was still open).
+    /// Returns the frames whose last pinning snapshot just went away, so the
+    /// caller can return them to the free list.
+    pub fn release_snapshot(&mut self, snapshot_id: SnapshotID) -> Vec<FrameID> {
+        if !self.active.remove(&snapshot_id) {
+            return Vec::new();
+        }
+
+        let mut freed = Vec::new();
+        self.chains.retain(|_page_id, chain| {
+            chain.versions.retain_mut(|entry| {
+                // This snapshot was only counted into versions frozen after it
+                // opened, i.e. versions whose `valid_before` it predates.
+                if entry.valid_before <= snapshot_id {
+                    return true;
+                }
+                entry.ref_count = entry.ref_count.saturating_sub(1);
+                if entry.ref_count == 0 {
+                    freed.push(entry.frame_id);
+                    false
+                } else {
+                    true
+                }
+            });
+            !chain.versions.is_empty()
+        });
+
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::page::page::Page;
+
+    fn dummy_page() -> Arc<RwLock<Page>> {
+        Arc::new(RwLock::new(Page::new()))
+    }
+
+    #[test]
+    fn release_frees_a_version_never_read_by_the_snapshot_that_covered_it() {
+        let mut snapshots = SnapshotManager::default();
+        let s1 = snapshots.begin_snapshot();
+        // s1 is open when page 1 is frozen, so it's pinned whether or not s1
+        // ever reads page 1.
+        snapshots.freeze_version(1, 7, dummy_page());
+
+        let freed = snapshots.release_snapshot(s1);
+        assert_eq!(freed, vec![7]);
+    }
+
+    #[test]
+    fn releasing_one_of_two_overlapping_snapshots_keeps_the_version_alive() {
+        let mut snapshots = SnapshotManager::default();
+        let s1 = snapshots.begin_snapshot();
+        let s2 = snapshots.begin_snapshot();
+        snapshots.freeze_version(1, 7, dummy_page());
+
+        // s1 releasing must not free the frame while s2 still covers it.
+        assert!(snapshots.release_snapshot(s1).is_empty());
+        assert_eq!(snapshots.release_snapshot(s2), vec![7]);
+    }
+
+    #[test]
+    fn reading_a_shared_version_twice_does_not_over_decrement_it() {
+        let mut snapshots = SnapshotManager::default();
+        let s1 = snapshots.begin_snapshot();
+        let s2 = snapshots.begin_snapshot();
+        snapshots.freeze_version(1, 7, dummy_page());
+
+        assert!(snapshots.find_version(1, s1).is_some());
+        assert!(snapshots.find_version(1, s1).is_some());
+        assert!(snapshots.find_version(1, s2).is_some());
+
+        assert!(snapshots.release_snapshot(s1).is_empty());
+        assert_eq!(snapshots.release_snapshot(s2), vec![7]);
+    }
+
+    #[test]
+    fn releasing_one_snapshot_does_not_disturb_an_older_sibling_version() {
+        let mut snapshots = SnapshotManager::default();
+        let s1 = snapshots.begin_snapshot();
+        snapshots.freeze_version(1, 7, dummy_page());
+        let s2 = snapshots.begin_snapshot();
+        snapshots.freeze_version(1, 8, dummy_page());
+
+        // s2 only covers the second version (frame 8), not the first (frame
+        // 7), which was already frozen before s2 even began.
+        assert!(snapshots.release_snapshot(s2).is_empty());
+        assert_eq!(snapshots.release_snapshot(s1), vec![7, 8]);
+    }
+
+    #[test]
+    fn a_snapshot_opened_after_freeze_does_not_see_or_pin_the_old_version() {
+        let mut snapshots = SnapshotManager::default();
+        let s1 = snapshots.begin_snapshot();
+        snapshots.freeze_version(1, 7, dummy_page());
+        let s2 = snapshots.begin_snapshot();
+
+        assert!(snapshots.find_version(1, s2).is_none());
+        assert!(snapshots.release_snapshot(s2).is_empty());
+        assert_eq!(snapshots.release_snapshot(s1), vec![7]);
+    }
+}
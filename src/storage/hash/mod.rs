@@ -0,0 +1,213 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+/// A single bucket in an `ExtendibleHashTable`'s directory. Each bucket owns a
+/// fixed-capacity run of entries and its own `local_depth`, which may lag behind
+/// the table's `global_depth` until the bucket itself is split.
+struct Bucket<K, V> {
+    local_depth: usize,
+    capacity: usize,
+    entries: Vec<(K, V)>,
+}
+
+impl<K: Eq + Clone, V: Clone> Bucket<K, V> {
+    fn new(local_depth: usize, capacity: usize) -> Self {
+        Self { local_depth, capacity, entries: Vec::new() }
+    }
+
+    fn is_full(&self) -> bool {
+        self.entries.len() >= self.capacity
+    }
+
+    fn find(&self, key: &K) -> Option<V> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        if let Some(pos) = self.entries.iter().position(|(k, _)| k == key) {
+            self.entries.remove(pos);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts or overwrites `key`. Returns `false` if the bucket is full and
+    /// `key` is not already present, in which case the caller must split first.
+    fn insert(&mut self, key: K, value: V) -> bool {
+        if let Some(slot) = self.entries.iter_mut().find(|(k, _)| *k == key) {
+            slot.1 = value;
+            return true;
+        }
+        if self.is_full() {
+            return false;
+        }
+        self.entries.push((key, value));
+        true
+    }
+}
+
+/// Bucket capacity used when an `ExtendibleHashTable` is constructed via
+/// `Default` rather than an explicit capacity.
+const DEFAULT_BUCKET_CAPACITY: usize = 4;
+
+/// An extendible hash table: a directory of bucket pointers indexed by the low
+/// `global_depth` bits of a key's hash, backed by fixed-capacity buckets that
+/// split independently without a global rehash. Used by the buffer pool manager
+/// as its `page_id -> frame_id` page table, mirroring how real DBMS page
+/// directories grow.
+pub struct ExtendibleHashTable<K, V> {
+    global_depth: usize,
+    bucket_capacity: usize,
+    directory: Vec<Arc<RwLock<Bucket<K, V>>>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ExtendibleHashTable<K, V> {
+    pub fn new(bucket_capacity: usize) -> Self {
+        Self {
+            global_depth: 0,
+            bucket_capacity,
+            directory: vec![Arc::new(RwLock::new(Bucket::new(0, bucket_capacity)))],
+        }
+    }
+
+    fn hash(key: &K) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn index_of(&self, key: &K) -> usize {
+        let mask = (1u64 << self.global_depth) - 1;
+        (Self::hash(key) & mask) as usize
+    }
+
+    pub fn find(&self, key: &K) -> Option<V> {
+        let index = self.index_of(key);
+        self.directory[index].read().expect("bucket rlock poisoned").find(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        let index = self.index_of(key);
+        self.directory[index].write().expect("bucket wlock poisoned").remove(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        loop {
+            let index = self.index_of(&key);
+            let bucket = self.directory[index].clone();
+            if bucket.write().expect("bucket wlock poisoned").insert(key.clone(), value.clone()) {
+                return;
+            }
+            self.split_bucket(index);
+        }
+    }
+
+    /// Splits the full bucket at `directory[index]`. If its `local_depth` has
+    /// caught up to `global_depth`, the directory is first doubled (each old
+    /// slot's pointer copied into its two images) and `global_depth` bumped.
+    /// The overflowing bucket's entries are then rehashed across itself and a
+    /// freshly allocated sibling using the newly incremented `local_depth` bit,
+    /// and every directory slot that pointed at the old bucket is fixed up to
+    /// point at whichever half now owns that slot's hash bit pattern.
+    fn split_bucket(&mut self, index: usize) {
+        let local_depth = self.directory[index].read().expect("bucket rlock poisoned").local_depth;
+
+        if local_depth == self.global_depth {
+            let old_len = self.directory.len();
+            self.directory.extend_from_within(0..old_len);
+            self.global_depth += 1;
+        }
+
+        let new_local_depth = local_depth + 1;
+        let split_bit = 1usize << local_depth;
+        let old_bucket = self.directory[index].clone();
+
+        let old_entries = {
+            let mut guard = old_bucket.write().expect("bucket wlock poisoned");
+            let entries = std::mem::take(&mut guard.entries);
+            guard.local_depth = new_local_depth;
+            entries
+        };
+        let sibling = Arc::new(RwLock::new(Bucket::new(new_local_depth, self.bucket_capacity)));
+
+        // Every directory slot that used to alias the old bucket keeps pointing
+        // at it unless the newly significant bit is set, in which case it now
+        // belongs to the fresh sibling half.
+        for slot_idx in 0..self.directory.len() {
+            if Arc::ptr_eq(&self.directory[slot_idx], &old_bucket) && (slot_idx & split_bit) != 0 {
+                self.directory[slot_idx] = sibling.clone();
+            }
+        }
+
+        for (k, v) in old_entries {
+            let target_index = self.index_of(&k);
+            self.directory[target_index]
+                .write()
+                .expect("bucket wlock poisoned")
+                .entries
+                .push((k, v));
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for ExtendibleHashTable<K, V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUCKET_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_inserted_keys() {
+        let mut table: ExtendibleHashTable<i32, i32> = ExtendibleHashTable::new(2);
+        table.insert(1, 100);
+        table.insert(2, 200);
+        assert_eq!(table.find(&1), Some(100));
+        assert_eq!(table.find(&2), Some(200));
+        assert_eq!(table.find(&3), None);
+    }
+
+    #[test]
+    fn insert_overwrites_existing_key() {
+        let mut table: ExtendibleHashTable<i32, i32> = ExtendibleHashTable::new(2);
+        table.insert(1, 100);
+        table.insert(1, 200);
+        assert_eq!(table.find(&1), Some(200));
+    }
+
+    #[test]
+    fn splitting_past_capacity_preserves_every_entry() {
+        let mut table: ExtendibleHashTable<i32, i32> = ExtendibleHashTable::new(2);
+        for key in 0..200 {
+            table.insert(key, key * 10);
+        }
+        for key in 0..200 {
+            assert_eq!(table.find(&key), Some(key * 10));
+        }
+    }
+
+    #[test]
+    fn remove_drops_key_without_disturbing_others() {
+        let mut table: ExtendibleHashTable<i32, i32> = ExtendibleHashTable::new(2);
+        for key in 0..50 {
+            table.insert(key, key);
+        }
+        assert!(table.remove(&10));
+        assert!(!table.contains_key(&10));
+        assert!(!table.remove(&10));
+        for key in 0..50 {
+            if key != 10 {
+                assert_eq!(table.find(&key), Some(key));
+            }
+        }
+    }
+}